@@ -0,0 +1,186 @@
+// This file is part of try-runtime-cli.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Providers of the `(InherentData, Digest)` pair needed to author the next block, used by
+//! [`crate::commands::fast_forward`].
+
+use cumulus_primitives_parachain_inherent::ParachainInherentData;
+use parity_scale_codec::Encode;
+use polkadot_primitives::{HeadData, PersistedValidationData};
+use sp_consensus_babe::{
+    digests::{CompatibleDigestItem, PreDigest, SecondaryPlainPreDigest},
+    Slot,
+};
+use sp_inherents::{InherentData, InherentDataProvider};
+use sp_runtime::{
+    generic::Digest,
+    traits::{Block as BlockT, Header as HeaderT},
+};
+
+use crate::inherents::custom_idps::ParaInherentDataProvider;
+
+/// Something that knows how to build the inherent data and the digest for the next block that
+/// [`crate::commands::fast_forward`] should author on top of a given parent header.
+#[async_trait::async_trait]
+pub trait BlockBuildingInfo<Block: BlockT> {
+    /// Build the `(InherentData, Digest)` pair for the block to be authored on top of `parent`.
+    async fn next_block_info(
+        &mut self,
+        parent: &Block::Header,
+    ) -> sc_cli::Result<(InherentData, Digest)>;
+}
+
+/// A [`BlockBuildingInfo`] for a plain timestamp + BABE chain, i.e. the vast majority of
+/// non-parachain Substrate runtimes.
+///
+/// Each call advances the timestamp by `blocktime_millis` and derives the BABE slot from it,
+/// emitting a `SecondaryPlain` pre-runtime digest so `Core_initialize_block` accepts the header.
+pub struct SubstrateInfo<Block> {
+    /// The timestamp, in milliseconds, that was used for the last authored block.
+    last_timestamp: u64,
+    /// The amount of milliseconds to advance the timestamp by for each authored block.
+    blocktime_millis: u64,
+    _phantom: std::marker::PhantomData<Block>,
+}
+
+impl<Block> SubstrateInfo<Block> {
+    /// Create a new instance, seeded with `last_timestamp` (typically the `Timestamp::Now` value
+    /// recorded in the state we are fast-forwarding from, so that the first authored block's
+    /// timestamp advances from the chain's own recorded time rather than jumping to wall-clock
+    /// "now").
+    pub fn new(blocktime_millis: u64, last_timestamp: u64) -> Self {
+        Self { last_timestamp, blocktime_millis, _phantom: Default::default() }
+    }
+}
+
+#[async_trait::async_trait]
+impl<Block: BlockT> BlockBuildingInfo<Block> for SubstrateInfo<Block> {
+    async fn next_block_info(
+        &mut self,
+        _parent: &Block::Header,
+    ) -> sc_cli::Result<(InherentData, Digest)> {
+        self.last_timestamp += self.blocktime_millis;
+
+        let mut inherent_data = InherentData::new();
+        sp_timestamp::InherentDataProvider::new(self.last_timestamp.into())
+            .provide_inherent_data(&mut inherent_data)
+            .await
+            .map_err(|e| {
+                sc_cli::Error::Application(
+                    format!("failed to build timestamp inherent: {:?}", e).into(),
+                )
+            })?;
+
+        let slot = Slot::from(self.last_timestamp / self.blocktime_millis);
+        let pre_digest = PreDigest::SecondaryPlain(SecondaryPlainPreDigest {
+            authority_index: 0,
+            slot,
+        });
+        let digest = Digest { logs: vec![CompatibleDigestItem::babe_pre_digest(pre_digest)] };
+
+        Ok((inherent_data, digest))
+    }
+}
+
+/// A [`BlockBuildingInfo`] for parachain (cumulus-based) runtimes.
+///
+/// In addition to everything [`SubstrateInfo`] provides, this also puts a (necessarily empty,
+/// since there is no collator network to source them from) [`polkadot_primitives::InherentData`]
+/// under `PARACHAINS_INHERENT_IDENTIFIER` via [`ParaInherentDataProvider`], and, for runtimes
+/// with the `parachain-system` pallet, a
+/// [`ParachainInherentData`] built from a synthesized relay-parent header whose number advances
+/// by one on every authored block.
+pub struct ParachainInfo<Block> {
+    substrate_info: SubstrateInfo<Block>,
+    /// The number given to the synthesized relay-parent of the next block.
+    next_relay_parent_number: polkadot_primitives::BlockNumber,
+    /// The previously synthesized relay-parent header, if any, so the next one can chain its
+    /// `parent_hash` from it instead of always using the zero hash.
+    last_relay_parent_header: Option<polkadot_primitives::Header>,
+}
+
+impl<Block> ParachainInfo<Block> {
+    /// Create a new instance, starting the synthesized relay chain at block 1. See
+    /// [`SubstrateInfo::new`] for the meaning of `last_timestamp`.
+    pub fn new(blocktime_millis: u64, last_timestamp: u64) -> Self {
+        Self {
+            substrate_info: SubstrateInfo::new(blocktime_millis, last_timestamp),
+            next_relay_parent_number: 1,
+            last_relay_parent_header: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<Block: BlockT> BlockBuildingInfo<Block> for ParachainInfo<Block> {
+    async fn next_block_info(
+        &mut self,
+        parent: &Block::Header,
+    ) -> sc_cli::Result<(InherentData, Digest)> {
+        let (mut inherent_data, digest) = self.substrate_info.next_block_info(parent).await?;
+
+        let parent_relay_hash = self
+            .last_relay_parent_header
+            .as_ref()
+            .map(|header| header.hash())
+            .unwrap_or_default();
+        let relay_parent_header = polkadot_primitives::Header::new(
+            self.next_relay_parent_number,
+            Default::default(),
+            Default::default(),
+            parent_relay_hash,
+            Default::default(),
+        );
+        self.next_relay_parent_number += 1;
+        self.last_relay_parent_header = Some(relay_parent_header.clone());
+
+        ParaInherentDataProvider::new(relay_parent_header.clone())
+            .provide_inherent_data(&mut inherent_data)
+            .await
+            .map_err(|e| {
+                sc_cli::Error::Application(format!("failed to build para inherent: {:?}", e).into())
+            })?;
+
+        // Best-effort `set_validation_data` inherent for `parachain-system`-based runtimes. Since
+        // we have no real relay chain, the persisted validation data is synthesized from the
+        // relay-parent header above; runtimes that don't include `parachain-system` simply never
+        // read this inherent back out, so it is harmless to always provide it.
+        let parachain_inherent_data = ParachainInherentData {
+            validation_data: PersistedValidationData {
+                parent_head: HeadData(parent.encode()),
+                relay_parent_number: self.next_relay_parent_number - 1,
+                relay_parent_storage_root: Default::default(),
+                max_pov_size: Default::default(),
+            },
+            relay_chain_state: Default::default(),
+            downward_messages: Default::default(),
+            horizontal_messages: Default::default(),
+        };
+        inherent_data
+            .put_data(
+                cumulus_primitives_parachain_inherent::INHERENT_IDENTIFIER,
+                &parachain_inherent_data,
+            )
+            .map_err(|e| {
+                sc_cli::Error::Application(
+                    format!("failed to build set_validation_data inherent: {:?}", e).into(),
+                )
+            })?;
+
+        Ok((inherent_data, digest))
+    }
+}
@@ -19,6 +19,7 @@ use std::{fmt::Debug, str::FromStr};
 
 use parity_scale_codec::Encode;
 use sc_executor::sp_wasm_interface::HostFunctions;
+use sc_rpc_api::chain::ListOrValue;
 use sp_runtime::{
     generic::SignedBlock,
     traits::{Block as BlockT, Header as HeaderT, NumberFor},
@@ -62,6 +63,32 @@ pub struct Command {
 	)]
     pub block_ws_uri: Option<String>,
 
+    /// The last block of the range to execute, as a block number or hash.
+    ///
+    /// When provided, every block in `[state's `--at`, to]` is replayed sequentially: the
+    /// externality is only scraped once, from the parent of the first block, and the storage
+    /// resulting from each block's execution is reused as the starting point for the next one.
+    /// When omitted, only the single block resolved by `state`'s `--at` is executed, as before.
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Whether to enable the state-root check.
+    ///
+    /// This compares the state root obtained after executing each block against the state root
+    /// recorded in its header, and is only meaningful when replaying a *released* runtime
+    /// against its own historical blocks. Leave this off when trying an unreleased runtime,
+    /// since its storage layout or logic may have legitimately diverged from what produced the
+    /// block.
+    #[arg(long)]
+    pub state_root_check: bool,
+
+    /// Whether to enable the signature check.
+    ///
+    /// This verifies the signature of every signed extrinsic in each block, and like
+    /// `state_root_check` is only meaningful when replaying a *released* runtime.
+    #[arg(long)]
+    pub signature_check: bool,
+
     /// The state type to use.
     #[command(subcommand)]
     pub state: State,
@@ -124,7 +151,7 @@ where
         }
     };
 
-    // The block we want to *execute* at is the block passed by the user
+    // The first block we want to *execute* is the block passed by the user
     let execute_at = live_state.at::<Block>()?;
 
     let prev_block_live_state = live_state.to_prev_block_live_state::<Block>().await?;
@@ -135,44 +162,145 @@ where
         version_increases: false,
         try_runtime_feature_enabled: true,
     };
-    let ext = State::Live(prev_block_live_state)
+    let mut ext = State::Live(prev_block_live_state)
         .to_ext::<Block, HostFns>(&shared, &executor, None, runtime_checks)
         .await?;
 
-    // Execute the desired block on top of it
-    let block =
-        ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::block(&rpc, execute_at)
+    // Resolve the `from` and `to` block numbers of the range to execute. Without `--to`, this is
+    // just `[execute_at, execute_at]`, i.e. the single-block behaviour from before.
+    let from_header =
+        ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::header(&rpc, execute_at)
             .await
             .map_err(rpc_err_handler)?
-            .expect("header exists, block should also exist; qed")
-            .block;
-
-    // A digest item gets added when the runtime is processing the block, so we need to pop
-    // the last one to be consistent with what a gossiped block would contain.
-    let (mut header, extrinsics) = block.deconstruct();
-    header.digest_mut().pop();
-    let block = Block::new(header, extrinsics);
-
-    // for now, hardcoded for the sake of simplicity. We might customize them one day.
-    let state_root_check = false;
-    let signature_check = false;
-    let payload = (
-        block.clone(),
-        state_root_check,
-        signature_check,
-        command.try_state,
-    )
-        .encode();
-
-    let _ = state_machine_call_with_proof::<Block, HostFns>(
-        &ext,
-        &mut Default::default(),
-        &executor,
-        "TryRuntime_execute_block",
-        &payload,
-        full_extensions(executor.clone()),
-        shared.export_proof,
-    )?;
+            .expect("header exists, block should also exist; qed");
+    let to_number = match &command.to {
+        Some(to) => {
+            let to_hash = match to.parse::<u64>() {
+                Ok(number) => match ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::block_hash(
+                    &rpc,
+                    Some(ListOrValue::Value(sp_rpc::number::NumberOrHex::Number(number))),
+                )
+                .await
+                .map_err(rpc_err_handler)?
+                {
+                    ListOrValue::Value(hash) => Some(
+                        hash.ok_or_else(|| format!("`--to` block #{} does not exist", number))?,
+                    ),
+                    ListOrValue::List(_) => unreachable!("a single `Value` query never returns a `List`; qed"),
+                },
+                Err(_) => LiveState {
+                    uri: block_ws_uri.clone(),
+                    at: Some(to.clone()),
+                    pallet: Default::default(),
+                    hashed_prefixes: Default::default(),
+                    child_tree: Default::default(),
+                }
+                .at::<Block>()?,
+            };
+            let to_header = ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::header(
+                &rpc, to_hash,
+            )
+            .await
+            .map_err(rpc_err_handler)?
+            .expect("header exists, block should also exist; qed");
+            *to_header.number()
+        }
+        None => *from_header.number(),
+    };
+
+    if to_number < *from_header.number() {
+        return Err(format!(
+            "`--to` ({:?}) resolves to a block before `state`'s `--at` ({:?}); did you swap them?",
+            to_number,
+            from_header.number()
+        )
+        .into())
+    }
+
+    let mut block_number = *from_header.number();
+    let mut block_hash = execute_at;
+    while block_number <= to_number {
+        log::info!(target: LOG_TARGET, "executing block {} ({:?})", block_number, block_hash);
+
+        let block = ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::block(
+            &rpc, block_hash,
+        )
+        .await
+        .map_err(rpc_err_handler)?
+        .expect("header exists, block should also exist; qed")
+        .block;
+
+        let (mut header, extrinsics) = block.deconstruct();
+        if !command.state_root_check {
+            // A digest item gets added when the runtime is processing the block, so we need to
+            // pop the last one to be consistent with what a gossiped block would contain. This
+            // is only needed to mimic a gossiped block for the lenient path: an exact
+            // state-root match requires the full stored block, digest included.
+            header.digest_mut().pop();
+        }
+        let next_block_hash = block_number_plus_one::<Block>(&rpc, &header, to_number).await?;
+        let block = Block::new(header, extrinsics);
+
+        let payload = (
+            block.clone(),
+            command.state_root_check,
+            command.signature_check,
+            command.try_state.clone(),
+        )
+            .encode();
+
+        // Pass `&mut ext` so the resulting storage is reused as the starting state of the next
+        // block in the range, rather than re-scraping state over RPC for every block.
+        let _ = state_machine_call_with_proof::<Block, HostFns>(
+            &mut ext,
+            &mut Default::default(),
+            &executor,
+            "TryRuntime_execute_block",
+            &payload,
+            full_extensions(executor.clone()),
+            shared.export_proof,
+        )?;
+
+        block_number += 1u32.into();
+        if let Some(hash) = next_block_hash {
+            block_hash = Some(hash);
+        }
+    }
 
     Ok(())
 }
+
+/// Resolve the hash of the block following `header`, unless `header` is already the last block
+/// of the range (`to_number`), in which case there is nothing left to fetch.
+async fn block_number_plus_one<Block>(
+    rpc: &substrate_rpc_client::WsClient,
+    header: &Block::Header,
+    to_number: NumberFor<Block>,
+) -> sc_cli::Result<Option<Block::Hash>>
+where
+    Block: BlockT + serde::de::DeserializeOwned,
+    Block::Hash: serde::de::DeserializeOwned,
+    Block::Header: serde::de::DeserializeOwned,
+    <NumberFor<Block> as TryInto<u64>>::Error: Debug,
+{
+    let next_number = *header.number() + 1u32.into();
+    if next_number > to_number {
+        return Ok(None)
+    }
+
+    let next_number_u64: u64 = next_number.try_into().expect("block number fits in u64; qed");
+    let next_hash = match ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::block_hash(
+        rpc,
+        Some(ListOrValue::Value(sp_rpc::number::NumberOrHex::Number(
+            next_number_u64,
+        ))),
+    )
+    .await
+    .map_err(rpc_err_handler)?
+    {
+        ListOrValue::Value(hash) => hash.expect("block number within range must have a hash; qed"),
+        ListOrValue::List(_) => unreachable!("a single `Value` query never returns a `List`; qed"),
+    };
+
+    Ok(Some(next_hash))
+}
@@ -0,0 +1,226 @@
+// This file is part of try-runtime-cli.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+
+use parity_scale_codec::{Decode, Encode};
+use sp_core::twox_128;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
+
+use crate::{
+    block_building_info::{BlockBuildingInfo, ParachainInfo, SubstrateInfo},
+    build_executor, full_extensions,
+    state::{RuntimeChecks, State},
+    state_machine_call, state_machine_call_with_proof, SharedParams, LOG_TARGET,
+};
+
+/// Configurations for [`run`].
+///
+/// Authors and executes `n_blocks` empty blocks on top of the given `state`, running the
+/// selected `try_state` checks after each one. Useful for exercising scheduled migrations,
+/// `on_initialize`/`on_finalize` weight, and era rotations without real blocks to replay.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Command {
+    /// Which try-state targets to execute when running this command.
+    ///
+    /// Expected values:
+    /// - `all`
+    /// - `none`
+    /// - A comma separated list of pallets, as per pallet names in `construct_runtime!()` (e.g.
+    ///   `Staking, System`).
+    /// - `rr-[x]` where `[x]` is a number. Then, the given number of pallets are checked in a
+    ///   round-robin fashion.
+    #[arg(long, default_value = "all")]
+    pub try_state: frame_try_runtime::TryStateSelect,
+
+    /// The number of empty blocks to author and execute on top of the given state.
+    #[arg(long, default_value = "1")]
+    pub n_blocks: u64,
+
+    /// The amount of milliseconds to advance the `sp_timestamp` inherent by for each authored
+    /// block. Also used to derive the BABE slot of each block.
+    ///
+    /// Must be greater than zero, since the BABE slot is derived by dividing the timestamp by
+    /// this value.
+    #[arg(long, default_value = "6000", value_parser = clap::value_parser!(u64).range(1..))]
+    pub blocktime_millis: u64,
+
+    /// Whether `state` belongs to a parachain (cumulus-based) runtime.
+    ///
+    /// When set, blocks are authored with a [`crate::block_building_info::ParachainInfo`]
+    /// instead of a plain [`crate::block_building_info::SubstrateInfo`], so that the para and
+    /// `set_validation_data` inherents are provided alongside the timestamp one.
+    #[arg(long)]
+    pub parachain: bool,
+
+    /// The state type to use.
+    #[command(subcommand)]
+    pub state: State,
+}
+
+/// The storage key of `System::Number`, used to recover the block number that `state` was
+/// scraped at, since neither `State::Live` nor `State::Snap` otherwise expose it generically.
+fn system_number_key() -> Vec<u8> {
+    [twox_128(b"System"), twox_128(b"Number")].concat()
+}
+
+/// The storage key of `Timestamp::Now`, used to recover the timestamp that `state` was scraped
+/// at, for the same reason as [`system_number_key`]. Seeding the block-building clock from this
+/// rather than wall-clock time matters most when fast-forwarding from an old archived snapshot
+/// (e.g. to bisect a regression), where wall-clock time may be months or years ahead of it.
+fn timestamp_now_key() -> Vec<u8> {
+    [twox_128(b"Timestamp"), twox_128(b"Now")].concat()
+}
+
+/// Runs the `fast-forward` command.
+pub async fn run<Block, HostFns>(shared: SharedParams, command: Command) -> sc_cli::Result<()>
+where
+    Block: BlockT + serde::de::DeserializeOwned,
+    Block::Hash: serde::de::DeserializeOwned,
+    Block::Header: serde::de::DeserializeOwned,
+    <NumberFor<Block> as TryInto<u64>>::Error: Debug,
+{
+    let executor = build_executor::<HostFns>(&shared);
+
+    let runtime_checks = RuntimeChecks {
+        name_matches: !shared.disable_spec_name_check,
+        version_increases: false,
+        try_runtime_feature_enabled: true,
+    };
+    let mut ext = command
+        .state
+        .to_ext::<Block, HostFns>(&shared, &executor, None, runtime_checks)
+        .await?;
+
+    let parent_number = ext
+        .execute_with(|| sp_io::storage::get(&system_number_key()))
+        .map(|raw| NumberFor::<Block>::decode(&mut &raw[..]))
+        .transpose()
+        .map_err(|e| format!("failed to decode `System::Number`: {:?}", e))?
+        .unwrap_or_default();
+
+    // Seed the block-building clock from the state's own recorded time rather than wall-clock
+    // time, falling back to the latter only when the key is absent (e.g. a genesis-only
+    // snapshot, which has never had `on_initialize` set `Timestamp::Now`).
+    let last_timestamp = ext
+        .execute_with(|| sp_io::storage::get(&timestamp_now_key()))
+        .map(|raw| u64::decode(&mut &raw[..]))
+        .transpose()
+        .map_err(|e| format!("failed to decode `Timestamp::Now`: {:?}", e))?
+        .unwrap_or_else(|| {
+            sp_timestamp::InherentDataProvider::from_system_time().timestamp().as_millis()
+        });
+
+    // We don't have a generic, RPC-independent way to recover the exact parent hash of `state`;
+    // since `fast-forward` always runs with `state_root_check = false` this is not
+    // security-relevant, it only needs to be internally consistent for the blocks we author.
+    let mut parent_header = <Block::Header as HeaderT>::new(
+        parent_number,
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        Default::default(),
+    );
+
+    let mut block_building_info: Box<dyn BlockBuildingInfo<Block>> = if command.parachain {
+        Box::new(ParachainInfo::<Block>::new(command.blocktime_millis, last_timestamp))
+    } else {
+        Box::new(SubstrateInfo::<Block>::new(command.blocktime_millis, last_timestamp))
+    };
+
+    for n in 1..=command.n_blocks {
+        let (inherent_data, digest) = block_building_info.next_block_info(&parent_header).await?;
+
+        let new_block_number = *parent_header.number() + 1u32.into();
+        let header = <Block::Header as HeaderT>::new(
+            new_block_number,
+            Default::default(),
+            Default::default(),
+            parent_header.hash(),
+            digest,
+        );
+
+        state_machine_call::<Block, HostFns>(
+            &mut ext,
+            &executor,
+            "Core_initialize_block",
+            &header.encode(),
+            full_extensions(executor.clone()),
+        )?;
+
+        let inherent_exts_raw = state_machine_call::<Block, HostFns>(
+            &mut ext,
+            &executor,
+            "BlockBuilder_inherent_extrinsics",
+            &inherent_data.encode(),
+            full_extensions(executor.clone()),
+        )?;
+        let inherent_exts = Vec::<Block::Extrinsic>::decode(&mut &*inherent_exts_raw)
+            .map_err(|e| format!("failed to decode inherent extrinsics: {:?}", e))?;
+
+        for xt in &inherent_exts {
+            state_machine_call::<Block, HostFns>(
+                &mut ext,
+                &executor,
+                "BlockBuilder_apply_extrinsic",
+                &xt.encode(),
+                full_extensions(executor.clone()),
+            )?;
+        }
+
+        let final_header_raw = state_machine_call::<Block, HostFns>(
+            &mut ext,
+            &executor,
+            "BlockBuilder_finalize_block",
+            &[],
+            full_extensions(executor.clone()),
+        )?;
+        let final_header = Block::Header::decode(&mut &*final_header_raw)
+            .map_err(|e| format!("failed to decode finalized header: {:?}", e))?;
+
+        let block = Block::new(final_header.clone(), inherent_exts);
+
+        log::info!(
+            target: LOG_TARGET,
+            "authoring and executing block {}/{} (#{:?})",
+            n,
+            command.n_blocks,
+            final_header.number(),
+        );
+
+        // `state_root_check` and `signature_check` are always disabled here: the block was just
+        // authored by us, not replayed, so neither check is meaningful.
+        let payload = (block, false, false, command.try_state.clone()).encode();
+
+        // `fast-forward` must carry the resulting storage forward into the next iteration, so,
+        // as `execute_block::run` does for a range of blocks, we pass `&mut ext` here, committing
+        // the changes back in rather than discarding them.
+        let _ = state_machine_call_with_proof::<Block, HostFns>(
+            &mut ext,
+            &mut Default::default(),
+            &executor,
+            "TryRuntime_execute_block",
+            &payload,
+            full_extensions(executor.clone()),
+            shared.export_proof,
+        )?;
+
+        parent_header = final_header;
+    }
+
+    Ok(())
+}
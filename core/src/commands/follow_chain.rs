@@ -0,0 +1,170 @@
+// This file is part of try-runtime-cli.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+
+use futures::StreamExt;
+use parity_scale_codec::Encode;
+use remote_externalities::TestExternalities;
+use sp_runtime::{
+    generic::SignedBlock,
+    traits::{Block as BlockT, Header as HeaderT, NumberFor},
+};
+use substrate_rpc_client::{ws_client, ChainApi};
+
+use crate::{
+    build_executor, full_extensions, rpc_err_handler,
+    state::{LiveState, RuntimeChecks, State},
+    state_machine_call_with_proof, SharedParams, LOG_TARGET,
+};
+
+/// Configurations for [`run`].
+///
+/// Unlike [`crate::commands::execute_block`], this subscribes to new finalized block headers as
+/// they arrive over `uri` and executes each block as soon as it is produced, making it suitable
+/// as a long-running runtime sanity monitor against a live node rather than a single replay.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Command {
+    /// Which try-state targets to execute when running this command.
+    ///
+    /// Expected values:
+    /// - `all`
+    /// - `none`
+    /// - A comma separated list of pallets, as per pallet names in `construct_runtime!()` (e.g.
+    ///   `Staking, System`).
+    /// - `rr-[x]` where `[x]` is a number. Then, the given number of pallets are checked in a
+    ///   round-robin fashion.
+    #[arg(long, default_value = "all")]
+    pub try_state: frame_try_runtime::TryStateSelect,
+
+    /// The ws uri from which to subscribe to finalized heads and fetch blocks.
+    #[arg(
+		long,
+		value_parser = crate::common::parse::url
+	)]
+    pub uri: String,
+}
+
+// Runs the `follow-chain` command.
+pub async fn run<Block, HostFns>(shared: SharedParams, command: Command) -> sc_cli::Result<()>
+where
+    Block: BlockT + serde::de::DeserializeOwned,
+    <Block::Hash as std::str::FromStr>::Err: Debug,
+    Block::Hash: serde::de::DeserializeOwned,
+    Block::Header: serde::de::DeserializeOwned,
+    <NumberFor<Block> as TryInto<u64>>::Error: Debug,
+    HostFns: sc_executor::sp_wasm_interface::HostFunctions,
+{
+    let executor = build_executor::<HostFns>(&shared);
+    let rpc = ws_client(&command.uri).await?;
+
+    let runtime_checks = RuntimeChecks {
+        name_matches: !shared.disable_spec_name_check,
+        version_increases: false,
+        try_runtime_feature_enabled: true,
+    };
+
+    // The externality carried forward between blocks, and the hash of the block it was built on
+    // top of. `None` until we've successfully executed at least one block, or whenever the next
+    // block's parent doesn't match (a reorg, or a gap caused by a missed subscription item).
+    let mut carried_over: Option<(Block::Hash, TestExternalities)> = None;
+
+    let mut subscription =
+        ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::subscribe_finalized_heads(
+            &rpc,
+        )
+        .await
+        .map_err(rpc_err_handler)?;
+
+    while let Some(notification) = subscription.next().await {
+        let header: Block::Header = match notification {
+            Ok(header) => header,
+            Err(why) => {
+                log::warn!(target: LOG_TARGET, "subscription returned an error: {:?}, skipping", why);
+                continue
+            }
+        };
+        let block_hash = header.hash();
+
+        let block =
+            ChainApi::<(), Block::Hash, Block::Header, SignedBlock<Block>>::block(
+                &rpc,
+                Some(block_hash),
+            )
+            .await
+            .map_err(rpc_err_handler)?
+            .expect("header exists, block should also exist; qed")
+            .block;
+
+        // A digest item gets added when the runtime is processing the block, so we need to pop
+        // the last one to be consistent with what a gossiped block would contain.
+        let (mut block_header, extrinsics) = block.deconstruct();
+        block_header.digest_mut().pop();
+        let parent_hash = *block_header.parent_hash();
+        let block = Block::new(block_header, extrinsics);
+
+        let mut ext = match carried_over.take() {
+            Some((prev_hash, ext)) if prev_hash == parent_hash => ext,
+            _ => {
+                log::debug!(target: LOG_TARGET, "scraping parent state of block {:?} fresh", block_hash);
+                let live_state = LiveState {
+                    uri: command.uri.clone(),
+                    at: Some(hex::encode(parent_hash.encode())),
+                    pallet: Default::default(),
+                    hashed_prefixes: Default::default(),
+                    child_tree: Default::default(),
+                };
+                State::Live(live_state)
+                    .to_ext::<Block, HostFns>(&shared, &executor, None, runtime_checks.clone())
+                    .await?
+            }
+        };
+
+        log::info!(target: LOG_TARGET, "executing block {} ({:?})", block.header().number(), block_hash);
+
+        // Both checks are always disabled here; unlike `execute_block`, `follow-chain` has no
+        // flags to enable them.
+        let state_root_check = false;
+        let signature_check = false;
+        let payload =
+            (block, state_root_check, signature_check, command.try_state.clone()).encode();
+
+        // Pass `&mut ext` so the post-execution storage is carried forward into the next
+        // iteration instead of being discarded, avoiding a state re-scrape for every block.
+        let result = state_machine_call_with_proof::<Block, HostFns>(
+            &mut ext,
+            &mut Default::default(),
+            &executor,
+            "TryRuntime_execute_block",
+            &payload,
+            full_extensions(executor.clone()),
+            shared.export_proof,
+        );
+
+        match result {
+            Ok(_) => carried_over = Some((block_hash, ext)),
+            Err(why) => {
+                log::error!(target: LOG_TARGET, "try_state failed at block {:?}: {:?}", block_hash, why);
+                // Don't carry forward state past a block that failed to execute; next iteration
+                // falls back to a fresh scrape.
+            }
+        }
+    }
+
+    log::info!(target: LOG_TARGET, "subscription closed, exiting `follow-chain`");
+    Ok(())
+}